@@ -0,0 +1,79 @@
+use bellman_ce::pairing::bn256::Bn256;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use powersoftau::batched_accumulator::BatchedAccumulator;
+use powersoftau::bn256::Bn256CeremonyParameters;
+use powersoftau::delay::{self, HashBackend};
+use powersoftau::keypair::keypair;
+use powersoftau::parameters::{CheckForCorrectness, PowersOfTauParameters, UseCompression};
+use rand::{SeedableRng, XorShiftRng};
+
+const START: [u8; 32] = [0u8; 32];
+const DELAY_ITERATIONS: u64 = 1 << 14;
+
+fn bench_delay_chain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("delay_chain");
+    for backend in [HashBackend::RustCrypto, HashBackend::Sha2] {
+        let hasher = backend.hasher();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{:?}", backend)),
+            &backend,
+            |b, _| {
+                b.iter(|| delay::iterate(hasher.as_ref(), black_box(START), DELAY_ITERATIONS));
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_calculate_hash(c: &mut Criterion) {
+    // An all-zero, appropriately-sized buffer is enough to exercise the
+    // hashing pass; it doesn't need to be a valid accumulator.
+    let buffer = vec![0u8; Bn256CeremonyParameters::ACCUMULATOR_BYTE_SIZE];
+
+    c.bench_function("calculate_hash", |b| {
+        b.iter(|| {
+            BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::calculate_hash(black_box(
+                &buffer,
+            ))
+        });
+    });
+}
+
+fn bench_transform(c: &mut Criterion) {
+    // A freshly generated initial accumulator (all group elements at the
+    // identity) is a valid, cheaply constructed input for `transform` —
+    // the operation's cost doesn't depend on which powers are accumulated.
+    let mut input = vec![0u8; Bn256CeremonyParameters::ACCUMULATOR_BYTE_SIZE];
+    BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::generate_initial_accumulator(&mut input);
+
+    let current_hash = BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::calculate_hash(&input);
+
+    let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let (_pubkey, privkey) = keypair::<_, Bn256>(&mut rng, current_hash.as_ref());
+
+    c.bench_function("transform", |b| {
+        b.iter_batched(
+            || vec![0u8; Bn256CeremonyParameters::CONTRIBUTION_BYTE_SIZE],
+            |mut output| {
+                BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::transform(
+                    black_box(&input),
+                    black_box(&mut output),
+                    UseCompression::No,
+                    UseCompression::Yes,
+                    CheckForCorrectness::No,
+                    &privkey,
+                )
+                .expect("transform must succeed on a freshly generated accumulator")
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_delay_chain,
+    bench_calculate_hash,
+    bench_transform
+);
+criterion_main!(benches);
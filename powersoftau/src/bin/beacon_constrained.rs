@@ -1,33 +1,251 @@
 use powersoftau::bn256::Bn256CeremonyParameters;
 
 use powersoftau::batched_accumulator::BatchedAccumulator;
+use powersoftau::delay::{self, HashBackend};
 use powersoftau::keypair::keypair;
 use powersoftau::parameters::{CheckForCorrectness, UseCompression};
 
 use bellman_ce::pairing::bn256::Bn256;
 use memmap::*;
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
 
 use std::io::Write;
+use std::ops::{Deref, DerefMut};
 
 use powersoftau::parameters::PowersOfTauParameters;
 
-#[macro_use]
-extern crate hex_literal;
+use serde::Serialize;
+use structopt::StructOpt;
+use zeroize::Zeroize;
+
+/// One of the (up to) 1024 interstitial hash states recorded while the beacon
+/// hash is being iterated, so that `verify_beacon` can recompute the
+/// chain between any two checkpoints independently of the others.
+#[derive(Debug, Serialize)]
+struct Checkpoint {
+    index: u64,
+    hash: String,
+}
 
-const INPUT_IS_COMPRESSED: UseCompression = UseCompression::No;
-const COMPRESS_THE_OUTPUT: UseCompression = UseCompression::Yes;
-const CHECK_INPUT_CORRECTNESS: CheckForCorrectness = CheckForCorrectness::No;
+/// A machine-readable record of a beacon run: enough to recompute the
+/// whole delay chain (in parallel, segment by segment) and confirm it
+/// ties together the starting block hash, the final beacon output, and
+/// the resulting contribution, without scraping stdout.
+#[derive(Debug, Serialize)]
+struct Transcript {
+    beacon_hash: String,
+    n: u64,
+    checkpoints: Vec<Checkpoint>,
+    beacon_output: String,
+    previous_accumulator_hash: String,
+    contribution_hash: String,
+}
 
-#[allow(clippy::modulo_one)]
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 3 {
-        println!("Usage: \n<challenge_file> <response_file>");
-        std::process::exit(exitcode::USAGE);
+/// Backing storage for the challenge/response files. `Mmap` is the
+/// original, zero-copy behaviour; `Buffered` reads the whole file into a
+/// `Vec<u8>` up front and writes it back once at the end, for filesystems
+/// and sandboxes where memory-mapping isn't available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IoMode {
+    Mmap,
+    Buffered,
+}
+
+fn parse_io_mode(src: &str) -> Result<IoMode, String> {
+    match src {
+        "mmap" => Ok(IoMode::Mmap),
+        "buffered" => Ok(IoMode::Buffered),
+        _ => Err(format!("expected `mmap` or `buffered`, got `{}`", src)),
+    }
+}
+
+/// Read-only backing for the challenge file, either a memory map or a
+/// buffer loaded once via `std::fs::read`.
+enum InputData {
+    Mmap(Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            InputData::Mmap(mmap) => mmap,
+            InputData::Buffered(buf) => buf,
+        }
+    }
+}
+
+/// Read-write backing for the response file, either a memory map or an
+/// in-memory buffer that's written back to disk once via `finalize`.
+enum OutputData {
+    Mmap(MmapMut),
+    Buffered(Vec<u8>),
+}
+
+impl Deref for OutputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            OutputData::Mmap(mmap) => mmap,
+            OutputData::Buffered(buf) => buf,
+        }
+    }
+}
+
+impl DerefMut for OutputData {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            OutputData::Mmap(mmap) => mmap,
+            OutputData::Buffered(buf) => buf,
+        }
+    }
+}
+
+impl OutputData {
+    /// Persists the contribution to `file`. The mmap variant is already
+    /// backed by the file and only needs flushing; the buffered variant
+    /// performs its one and only write here.
+    fn finalize(self, file: &File) -> std::io::Result<()> {
+        match self {
+            OutputData::Mmap(mut mmap) => mmap.flush(),
+            OutputData::Buffered(buf) => {
+                use std::io::{Seek, SeekFrom};
+                (&*file).seek(SeekFrom::Start(0))?;
+                (&*file).write_all(&buf)
+            }
+        }
     }
-    let challenge_filename = &args[1];
-    let response_filename = &args[2];
+}
+
+/// Applies a random beacon to a powers of tau accumulator.
+///
+/// The beacon hash, the number of delay iterations, and the compression/
+/// correctness flags used to previously be baked into this binary at
+/// compile time. They're now passed on the command line (or recorded in a
+/// config file and replayed with `@config_file`: `main` replaces such an
+/// argument with the whitespace-separated arguments stored in that file)
+/// so that one compiled binary can be reused across
+/// ceremonies with different parameters, and so a run can be reproduced
+/// from the arguments alone.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "beacon_constrained",
+    about = "Apply a random beacon to a powers of tau accumulator",
+    setting = structopt::clap::AppSettings::ColoredHelp
+)]
+struct BeaconOpts {
+    /// Hex-encoded block hash (or other public, unpredictable value) used
+    /// to seed the random beacon.
+    #[structopt(long, parse(try_from_str = parse_beacon_hash))]
+    beacon_hash: [u8; 32],
+
+    /// Number of delay iterations to apply to the beacon hash, expressed
+    /// as the exponent of a power of two (i.e. the hash is iterated
+    /// `2^n` times).
+    #[structopt(long, default_value = "10")]
+    n: u64,
+
+    /// Whether the challenge file is stored in compressed form.
+    #[structopt(long, parse(try_from_str = parse_bool))]
+    input_is_compressed: bool,
+
+    /// Whether the response file should be written in compressed form.
+    #[structopt(long, parse(try_from_str = parse_bool))]
+    compress_the_output: bool,
+
+    /// Whether to fully check the challenge file's points for
+    /// correctness. This is slow, and only necessary if the challenge
+    /// file's provenance isn't already trusted.
+    #[structopt(long, parse(try_from_str = parse_bool))]
+    check_input_correctness: bool,
+
+    /// How the challenge/response files are read and written: `mmap`
+    /// memory-maps them (the default, zero-copy), `buffered` reads/writes
+    /// them through plain `Vec<u8>` buffers for filesystems or sandboxes
+    /// where memory-mapping isn't available.
+    #[structopt(long, default_value = "mmap", parse(try_from_str = parse_io_mode))]
+    io_mode: IoMode,
+
+    /// Path to write a JSON transcript of this run to, recording the
+    /// beacon's checkpoints and the resulting hashes. `verify_beacon` can
+    /// independently re-derive every segment of the chain from this file.
+    #[structopt(long, default_value = "transcript.json")]
+    transcript_filename: String,
+
+    /// Which SHA256 implementation drives the beacon's delay chain:
+    /// `rust-crypto` (the historical default) or `sha2`, which picks up
+    /// CPU SHA extensions at runtime. Both produce identical output.
+    #[structopt(long, default_value = "rust-crypto")]
+    hash_backend: HashBackend,
+
+    /// Path to the challenge file produced by the previous participant.
+    challenge_filename: String,
+
+    /// Path to write this contribution's response file to.
+    response_filename: String,
+}
+
+fn parse_bool(src: &str) -> Result<bool, String> {
+    match src {
+        "yes" | "true" => Ok(true),
+        "no" | "false" => Ok(false),
+        _ => Err(format!("expected `yes`/`no`, got `{}`", src)),
+    }
+}
+
+fn parse_beacon_hash(src: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(src.trim_start_matches("0x"))
+        .map_err(|e| format!("beacon hash must be valid hex: {}", e))?;
+    let mut hash = [0u8; 32];
+    if bytes.len() != hash.len() {
+        return Err(format!(
+            "beacon hash must be exactly {} bytes ({} hex characters), got {} bytes",
+            hash.len(),
+            hash.len() * 2,
+            bytes.len()
+        ));
+    }
+    hash.copy_from_slice(&bytes);
+    Ok(hash)
+}
+
+fn as_use_compression(flag: bool) -> UseCompression {
+    if flag {
+        UseCompression::Yes
+    } else {
+        UseCompression::No
+    }
+}
+
+fn as_check_for_correctness(flag: bool) -> CheckForCorrectness {
+    if flag {
+        CheckForCorrectness::Full
+    } else {
+        CheckForCorrectness::No
+    }
+}
+
+fn main() {
+    let args = std::env::args().flat_map(|arg| {
+        if arg.starts_with('@') {
+            std::fs::read_to_string(&arg[1..])
+                .expect("unable to read config file")
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        } else {
+            vec![arg]
+        }
+    });
+    let opts = BeaconOpts::from_iter(args);
+
+    let beacon_hash = opts.beacon_hash;
+    let input_is_compressed = as_use_compression(opts.input_is_compressed);
+    let compress_the_output = as_use_compression(opts.compress_the_output);
+    let check_input_correctness = as_check_for_correctness(opts.check_input_correctness);
 
     println!(
         "Will contribute a random beacon to accumulator for 2^{} powers of tau",
@@ -37,45 +255,53 @@ fn main() {
         "In total will generate up to {} powers",
         Bn256CeremonyParameters::TAU_POWERS_G1_LENGTH
     );
+    println!("Configuration for this run:");
+    println!("\tbeacon hash: {}", hex::encode(opts.beacon_hash));
+    println!("\tdelay iterations: 2^{}", opts.n);
+    println!("\tinput is compressed: {}", opts.input_is_compressed);
+    println!("\tcompress the output: {}", opts.compress_the_output);
+    println!(
+        "\tcheck input correctness: {}",
+        opts.check_input_correctness
+    );
+    println!("\tio mode: {:?}", opts.io_mode);
+    println!("\thash backend: {:?}", opts.hash_backend);
+
+    let mut checkpoints = Vec::new();
+    let mut beacon_output = String::new();
 
     // Create an RNG based on the outcome of the random beacon
     let mut rng = {
         use byteorder::{BigEndian, ReadBytesExt};
-        use crypto::digest::Digest;
-        use crypto::sha2::Sha256;
         use rand::chacha::ChaChaRng;
         use rand::SeedableRng;
 
-        // Place block hash here (block number #564321)
-        let mut cur_hash: [u8; 32] =
-            hex!("0000000000000000000a558a61ddc8ee4e488d647a747fe4dcc362fe2026c620");
+        let hasher = opts.hash_backend.hasher();
 
-        // Performs 2^n hash iterations over it
-        const N: u64 = 10;
+        let total = 1u64 << opts.n;
+        let step = 1u64 << opts.n.saturating_sub(10);
 
-        for i in 0..(1u64 << N) {
-            // Print 1024 of the interstitial states
-            // so that verification can be
+        let mut cur_hash = beacon_hash;
+        let mut i = 0u64;
+        while i < total {
+            // Record (up to) 1024 of the interstitial states, both to stdout and
+            // to the transcript, so that verification can be
             // parallelized
 
-            if i % (1u64 << (N - 10)) == 0 {
-                print!("{}: ", i);
-                for b in cur_hash.iter() {
-                    print!("{:02x}", b);
-                }
-                println!();
-            }
+            let hash_hex = hex::encode(cur_hash);
+            println!("{}: {}", i, hash_hex);
+            checkpoints.push(Checkpoint {
+                index: i,
+                hash: hash_hex,
+            });
 
-            let mut h = Sha256::new();
-            h.input(&cur_hash);
-            h.result(&mut cur_hash);
+            let this_step = step.min(total - i);
+            cur_hash = delay::iterate(hasher.as_ref(), cur_hash, this_step);
+            i += this_step;
         }
 
-        print!("Final result of beacon: ");
-        for b in cur_hash.iter() {
-            print!("{:02x}", b);
-        }
-        println!();
+        beacon_output = hex::encode(cur_hash);
+        println!("Final result of beacon: {}", beacon_output);
 
         let mut digest = &cur_hash[..];
 
@@ -86,7 +312,15 @@ fn main() {
                 .expect("digest is large enough for this to work");
         }
 
-        ChaChaRng::from_seed(&seed)
+        let rng = ChaChaRng::from_seed(&seed);
+
+        // The beacon hash has now served its purpose; scrub it (and the
+        // seed derived from it) from the stack rather than leaving it to
+        // be overwritten incidentally.
+        cur_hash.zeroize();
+        seed.zeroize();
+
+        rng
     };
 
     println!("Done creating a beacon RNG");
@@ -94,14 +328,14 @@ fn main() {
     // Try to load challenge file from disk.
     let reader = OpenOptions::new()
         .read(true)
-        .open(challenge_filename)
+        .open(&opts.challenge_filename)
         .expect("unable open challenge file in this directory");
 
     {
         let metadata = reader
             .metadata()
             .expect("unable to get filesystem metadata for challenge file");
-        let expected_challenge_length = match INPUT_IS_COMPRESSED {
+        let expected_challenge_length = match input_is_compressed {
             UseCompression::Yes => Bn256CeremonyParameters::CONTRIBUTION_BYTE_SIZE,
             UseCompression::No => Bn256CeremonyParameters::ACCUMULATOR_BYTE_SIZE,
         };
@@ -115,10 +349,16 @@ fn main() {
         }
     }
 
-    let readable_map = unsafe {
-        MmapOptions::new()
-            .map(&reader)
-            .expect("unable to create a memory map for input")
+    let readable_map = match opts.io_mode {
+        IoMode::Mmap => InputData::Mmap(unsafe {
+            MmapOptions::new()
+                .map(&reader)
+                .expect("unable to create a memory map for input")
+        }),
+        IoMode::Buffered => InputData::Buffered(
+            std::fs::read(&opts.challenge_filename)
+                .expect("unable to read challenge file into memory"),
+        ),
     };
 
     // Create response file in this directory
@@ -126,10 +366,10 @@ fn main() {
         .read(true)
         .write(true)
         .create_new(true)
-        .open(response_filename)
+        .open(&opts.response_filename)
         .expect("unable to create response file in this directory");
 
-    let required_output_length = match COMPRESS_THE_OUTPUT {
+    let required_output_length = match compress_the_output {
         UseCompression::Yes => Bn256CeremonyParameters::CONTRIBUTION_BYTE_SIZE,
         UseCompression::No => {
             Bn256CeremonyParameters::ACCUMULATOR_BYTE_SIZE
@@ -141,16 +381,20 @@ fn main() {
         .set_len(required_output_length as u64)
         .expect("must make output file large enough");
 
-    let mut writable_map = unsafe {
-        MmapOptions::new()
-            .map_mut(&writer)
-            .expect("unable to create a memory map for output")
+    let mut writable_map = match opts.io_mode {
+        IoMode::Mmap => OutputData::Mmap(unsafe {
+            MmapOptions::new()
+                .map_mut(&writer)
+                .expect("unable to create a memory map for output")
+        }),
+        IoMode::Buffered => OutputData::Buffered(vec![0u8; required_output_length]),
     };
 
     println!("Calculating previous contribution hash...");
 
     let current_accumulator_hash =
         BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::calculate_hash(&readable_map);
+    let previous_accumulator_hash = hex::encode(current_accumulator_hash.as_slice());
 
     {
         println!("Contributing on top of the hash:");
@@ -167,11 +411,7 @@ fn main() {
 
         (&mut writable_map[0..])
             .write_all(current_accumulator_hash.as_slice())
-            .expect("unable to write a challenge hash to mmap");
-
-        writable_map
-            .flush()
-            .expect("unable to write hash to response file");
+            .expect("unable to write a challenge hash to the output buffer");
     }
 
     // Construct our keypair using the RNG we created above
@@ -184,25 +424,34 @@ fn main() {
     BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::transform(
         &readable_map,
         &mut writable_map,
-        INPUT_IS_COMPRESSED,
-        COMPRESS_THE_OUTPUT,
-        CHECK_INPUT_CORRECTNESS,
+        input_is_compressed,
+        compress_the_output,
+        check_input_correctness,
         &privkey,
     )
     .expect("must transform with the key");
     println!("Finishing writing your contribution to response file...");
 
-    // Write the public key
+    // The private key has done its job; drop it now (rather than at the
+    // end of `main`) so `ZeroizeOnDrop` scrubs the secret tau/alpha/beta
+    // scalars from memory as soon as they're no longer needed.
+    drop(privkey);
+
+    // Write the public key into the last PUBLIC_KEY_SIZE bytes of the response
     pubkey
-        .write::<Bn256CeremonyParameters>(&mut writable_map, COMPRESS_THE_OUTPUT)
+        .write::<Bn256CeremonyParameters>(
+            &mut writable_map[required_output_length - Bn256CeremonyParameters::PUBLIC_KEY_SIZE..],
+            compress_the_output,
+        )
         .expect("unable to write public key");
 
     // Get the hash of the contribution, so the user can compare later
-    let output_readonly = writable_map
-        .make_read_only()
-        .expect("must make a map readonly");
     let contribution_hash =
-        BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::calculate_hash(&output_readonly);
+        BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::calculate_hash(&writable_map);
+
+    writable_map
+        .finalize(&writer)
+        .expect("unable to write response file to disk");
 
     print!(
         "Done!\n\n\
@@ -221,5 +470,24 @@ fn main() {
         println!();
     }
 
+    let transcript = Transcript {
+        // Recorded as the normalized, lowercase, unprefixed hex `hex::encode`
+        // produces (matching `checkpoints[0].hash`) rather than the raw CLI
+        // string, so `verify_beacon` can compare them byte-for-byte without
+        // having to second-guess the operator's casing or `0x` prefix.
+        beacon_hash: hex::encode(beacon_hash),
+        n: opts.n,
+        checkpoints,
+        beacon_output,
+        previous_accumulator_hash,
+        contribution_hash: hex::encode(contribution_hash.as_slice()),
+    };
+    std::fs::write(
+        &opts.transcript_filename,
+        serde_json::to_string_pretty(&transcript).expect("transcript must serialize to JSON"),
+    )
+    .expect("unable to write transcript file");
+    println!("Wrote transcript to {}", opts.transcript_filename);
+
     println!("Thank you for your participation, much appreciated! :)");
 }
@@ -0,0 +1,212 @@
+use powersoftau::batched_accumulator::BatchedAccumulator;
+use powersoftau::bn256::Bn256CeremonyParameters;
+use powersoftau::keypair::keypair;
+use powersoftau::parameters::{PowersOfTauParameters, UseCompression};
+use rayon::prelude::*;
+use serde::Deserialize;
+use structopt::StructOpt;
+
+use bellman_ce::pairing::bn256::Bn256;
+
+use powersoftau::delay::{self, HashBackend};
+
+/// Independently recomputes the SHA256 delay chain recorded in a beacon
+/// transcript (see `beacon_constrained`), segment by segment in parallel,
+/// confirms that the chain joins up end to end, and (when `--response-file`
+/// is given) confirms that the response file's embedded public key is the
+/// one `keypair()` would actually derive from the recorded beacon output
+/// and previous accumulator hash — the only check that ties the
+/// contribution to the beacon seed, as opposed to merely confirming the
+/// file wasn't corrupted after the fact. Without `--response-file` this
+/// tool only checks the delay chain's arithmetic — it will say so rather
+/// than reporting full success.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "verify_beacon",
+    about = "Verify a beacon transcript's delay chain independently and in parallel"
+)]
+struct VerifyBeaconOpts {
+    /// Path to the JSON transcript produced by `beacon_constrained`.
+    transcript_filename: String,
+
+    /// Which SHA256 implementation to recompute the delay chain with.
+    /// Any backend reproduces the same output, so this only affects how
+    /// fast verification runs.
+    #[structopt(long, default_value = "rust-crypto")]
+    hash_backend: HashBackend,
+
+    /// Path to the response file the beacon contribution was written to.
+    /// When given, its contribution hash is checked against the
+    /// transcript, and the public key it embeds is checked against the
+    /// one `keypair()` derives from the recorded beacon output and
+    /// previous accumulator hash — tying the file to the beacon seed, not
+    /// just to itself. When omitted, neither check runs: only the delay
+    /// chain's arithmetic is verified.
+    #[structopt(long)]
+    response_file: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Checkpoint {
+    index: u64,
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transcript {
+    beacon_hash: String,
+    n: u64,
+    checkpoints: Vec<Checkpoint>,
+    beacon_output: String,
+    previous_accumulator_hash: String,
+    contribution_hash: String,
+}
+
+fn decode_hash(hex_str: &str) -> [u8; 32] {
+    let bytes = hex::decode(hex_str).expect("checkpoint hash must be valid hex");
+    let mut hash = [0u8; 32];
+    assert_eq!(bytes.len(), hash.len(), "checkpoint hash must be 32 bytes");
+    hash.copy_from_slice(&bytes);
+    hash
+}
+
+fn derive_seed(final_hash: [u8; 32]) -> [u32; 8] {
+    use byteorder::{BigEndian, ReadBytesExt};
+
+    let mut digest = &final_hash[..];
+    let mut seed = [0u32; 8];
+    for s in &mut seed {
+        *s = digest
+            .read_u32::<BigEndian>()
+            .expect("digest is large enough for this to work");
+    }
+    seed
+}
+
+fn main() {
+    let opts = VerifyBeaconOpts::from_args();
+
+    let transcript: Transcript = serde_json::from_slice(
+        &std::fs::read(&opts.transcript_filename).expect("unable to read transcript file"),
+    )
+    .expect("transcript is not valid JSON");
+
+    assert!(
+        !transcript.checkpoints.is_empty(),
+        "transcript has no checkpoints to verify"
+    );
+    assert_eq!(
+        transcript.checkpoints[0].hash, transcript.beacon_hash,
+        "first checkpoint must be the recorded beacon hash"
+    );
+
+    // Each pair of consecutive checkpoints delimits an independent
+    // segment of the delay chain, so they can all be recomputed at once.
+    let segments: Vec<(&str, &str, u64)> = transcript
+        .checkpoints
+        .windows(2)
+        .map(|w| (w[0].hash.as_str(), w[1].hash.as_str(), w[1].index - w[0].index))
+        .collect();
+
+    segments.par_iter().for_each(|(start_hex, end_hex, iterations)| {
+        let hasher = opts.hash_backend.hasher();
+        let start = decode_hash(start_hex);
+        let recomputed = delay::iterate(hasher.as_ref(), start, *iterations);
+        assert_eq!(
+            hex::encode(recomputed),
+            *end_hex,
+            "chain does not join up between checkpoints starting at {}",
+            start_hex
+        );
+    });
+
+    println!(
+        "Verified {} independent chain segment(s) up to the last checkpoint",
+        segments.len()
+    );
+
+    // The tail of the chain, from the last checkpoint to the recorded
+    // final output, is just one more segment.
+    let last = transcript
+        .checkpoints
+        .last()
+        .expect("checked non-empty above");
+    let last_hash = decode_hash(&last.hash);
+    let final_iterations = (1u64 << transcript.n) - last.index;
+    let recomputed_final = delay::iterate(opts.hash_backend.hasher().as_ref(), last_hash, final_iterations);
+    assert_eq!(
+        hex::encode(recomputed_final),
+        transcript.beacon_output,
+        "chain does not reach the recorded beacon output"
+    );
+    println!("Verified the chain reaches the recorded beacon output");
+
+    let seed = derive_seed(decode_hash(&transcript.beacon_output));
+    println!(
+        "Derived RNG seed from the recorded beacon output: {:?}",
+        seed
+    );
+
+    match &opts.response_file {
+        Some(response_filename) => {
+            let response =
+                std::fs::read(response_filename).expect("unable to read response file");
+
+            // Catches post-hoc corruption of the response file, but not
+            // whether it was actually derived from the beacon seed: a
+            // contribution built from an unrelated private key would
+            // still self-report a contribution_hash that trivially
+            // matches its own bytes.
+            let recomputed_contribution_hash =
+                BatchedAccumulator::<Bn256, Bn256CeremonyParameters>::calculate_hash(&response);
+            assert_eq!(
+                hex::encode(recomputed_contribution_hash.as_slice()),
+                transcript.contribution_hash,
+                "response file's contribution hash does not match the one recorded in the transcript"
+            );
+
+            // The real tie to the beacon: rebuild the RNG from the
+            // recorded beacon output, reconstruct the PublicKey that
+            // `keypair()` would have derived from it and the previous
+            // accumulator hash, and check it against the bytes actually
+            // embedded in the response file.
+            let mut rng = {
+                use rand::chacha::ChaChaRng;
+                use rand::SeedableRng;
+
+                ChaChaRng::from_seed(&derive_seed(decode_hash(&transcript.beacon_output)))
+            };
+
+            let previous_accumulator_hash = hex::decode(&transcript.previous_accumulator_hash)
+                .expect("previous_accumulator_hash must be valid hex");
+            let (expected_pubkey, _expected_privkey) =
+                keypair::<_, Bn256>(&mut rng, &previous_accumulator_hash);
+
+            let mut expected_pubkey_bytes = vec![0u8; Bn256CeremonyParameters::PUBLIC_KEY_SIZE];
+            expected_pubkey
+                .write::<Bn256CeremonyParameters>(&mut expected_pubkey_bytes, UseCompression::Yes)
+                .expect("unable to serialize the expected public key");
+
+            let actual_pubkey_bytes =
+                &response[response.len() - Bn256CeremonyParameters::PUBLIC_KEY_SIZE..];
+            assert_eq!(
+                expected_pubkey_bytes, actual_pubkey_bytes,
+                "public key embedded in the response file was not derived from the recorded beacon seed"
+            );
+
+            println!(
+                "Verified {} was derived from the recorded beacon seed and hashes to the transcript's contribution hash",
+                response_filename
+            );
+        }
+        None => {
+            println!(
+                "No --response-file given: the delay chain and beacon output were verified, \
+                 but the tie to the recorded contribution hash {} was NOT checked.",
+                transcript.contribution_hash
+            );
+        }
+    }
+
+    println!("Beacon transcript is internally consistent.");
+}
@@ -0,0 +1,118 @@
+//! The single SHA256 step applied, over and over, by the beacon's
+//! iterated delay function (see `bin/beacon_constrained.rs`).
+
+/// A backend for the SHA256 step used in the beacon's iterated delay
+/// function. `RustCryptoSha256` is the backend this binary has always
+/// used; `Sha2Sha256` uses the `sha2` crate, which takes advantage of CPU
+/// SHA extensions at runtime and is substantially faster on hardware that
+/// supports them. Both must produce identical output for a given input,
+/// since the backend is purely a performance knob, not a behavior change.
+pub trait DelayHasher {
+    fn hash(&self, input: &[u8; 32]) -> [u8; 32];
+}
+
+pub struct RustCryptoSha256;
+
+impl DelayHasher for RustCryptoSha256 {
+    fn hash(&self, input: &[u8; 32]) -> [u8; 32] {
+        use crypto::digest::Digest;
+        use crypto::sha2::Sha256;
+
+        let mut out = [0u8; 32];
+        let mut h = Sha256::new();
+        h.input(input);
+        h.result(&mut out);
+        out
+    }
+}
+
+pub struct Sha2Sha256;
+
+impl DelayHasher for Sha2Sha256 {
+    fn hash(&self, input: &[u8; 32]) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&Sha256::digest(input));
+        out
+    }
+}
+
+/// Selects a [`DelayHasher`] implementation at runtime, e.g. from a CLI
+/// flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashBackend {
+    RustCrypto,
+    Sha2,
+}
+
+impl std::str::FromStr for HashBackend {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "rust-crypto" => Ok(HashBackend::RustCrypto),
+            "sha2" => Ok(HashBackend::Sha2),
+            _ => Err(format!("expected `rust-crypto` or `sha2`, got `{}`", src)),
+        }
+    }
+}
+
+impl HashBackend {
+    pub fn hasher(self) -> Box<dyn DelayHasher> {
+        match self {
+            HashBackend::RustCrypto => Box::new(RustCryptoSha256),
+            HashBackend::Sha2 => Box::new(Sha2Sha256),
+        }
+    }
+}
+
+/// Applies `iterations` sequential hashes of `start` using `backend`,
+/// returning the resulting state. A single checkpoint-to-checkpoint
+/// segment of the beacon's delay chain is exactly one call to this
+/// function, which is what lets `verify_beacon` recompute segments
+/// independently and in parallel.
+pub fn iterate(backend: &dyn DelayHasher, start: [u8; 32], iterations: u64) -> [u8; 32] {
+    let mut cur_hash = start;
+    for _ in 0..iterations {
+        cur_hash = backend.hash(&cur_hash);
+    }
+    cur_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `verify_beacon` lets one run record checkpoints with one backend
+    /// and a later process re-verify them with the other, which only
+    /// makes sense if both backends really do agree on every input. This
+    /// pins that invariant down so a future change to either backend that
+    /// breaks it fails here, not as a silent cross-backend verification
+    /// mismatch.
+    #[test]
+    fn backends_agree() {
+        let inputs = [
+            [0u8; 32],
+            [0xffu8; 32],
+            *b"01234567890123456789012345678901",
+        ];
+
+        for input in inputs {
+            assert_eq!(
+                RustCryptoSha256.hash(&input),
+                Sha2Sha256.hash(&input),
+                "rust-crypto and sha2 backends disagree on input {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn iterate_agrees_across_backends() {
+        let start = [0x42u8; 32];
+        let rust_crypto_result = iterate(&RustCryptoSha256, start, 37);
+        let sha2_result = iterate(&Sha2Sha256, start, 37);
+        assert_eq!(rust_crypto_result, sha2_result);
+    }
+}
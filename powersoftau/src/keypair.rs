@@ -0,0 +1,155 @@
+use bellman_ce::pairing::{CurveAffine, CurveProjective, EncodedPoint, Engine, Field};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use rand::Rng;
+use std::io::{self, Read, Write};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::parameters::{DeserializationError, PowersOfTauParameters};
+use super::utils::{compute_g2_s, hash_to_g2};
+
+/// Contains the terms (s<sub>1</sub>, s<sub>1</sub><sup>x</sup>, H(s<sub>1</sub><sup>x</sup>)<sub>2</sub>,
+/// H(s<sub>1</sub><sup>x</sup>)<sub>2</sub><sup>x</sup>) for all of tau, alpha, and beta, where `x` is
+/// the secret contributed in this round. Anyone can use this to verify a
+/// contribution was performed correctly without learning the secret itself.
+pub struct PublicKey<E: Engine> {
+    pub tau_g1: (E::G1Affine, E::G1Affine),
+    pub alpha_g1: (E::G1Affine, E::G1Affine),
+    pub beta_g1: (E::G1Affine, E::G1Affine),
+    pub tau_g2: E::G2Affine,
+    pub alpha_g2: E::G2Affine,
+    pub beta_g2: E::G2Affine,
+}
+
+/// Holds the secret scalars `tau`, `alpha`, and `beta` that the
+/// contributor must destroy once the transformation has been applied.
+/// This is the "toxic waste" of the ceremony: whoever holds it can forge
+/// proofs, so it implements `Zeroize`/`ZeroizeOnDrop` to scrub itself from
+/// memory as soon as it's dropped, rather than relying on the contributor
+/// to remember to do so.
+///
+/// `E::Fr` is an associated type of `bellman_ce`'s `Engine`/`pairing`
+/// traits, which predate `zeroize` and don't implement it, so `Zeroize`
+/// can't be derived here. Instead each scalar is overwritten in place
+/// with `E::Fr::zero()` via a volatile write, so the store can't be
+/// optimized away as dead code the way a plain `*scalar = ...` could be.
+pub struct PrivateKey<E: Engine> {
+    pub tau: E::Fr,
+    pub alpha: E::Fr,
+    pub beta: E::Fr,
+}
+
+impl<E: Engine> Zeroize for PrivateKey<E> {
+    fn zeroize(&mut self) {
+        for scalar in [&mut self.tau, &mut self.alpha, &mut self.beta] {
+            // SAFETY: `scalar` is a valid, aligned `&mut E::Fr` borrowed
+            // from `self`; a volatile write to it is always sound, and
+            // using one here (rather than a plain `*scalar = ...`)
+            // prevents the compiler from eliding the store as dead code.
+            unsafe {
+                std::ptr::write_volatile(scalar, E::Fr::zero());
+            }
+        }
+        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+impl<E: Engine> Drop for PrivateKey<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<E: Engine> ZeroizeOnDrop for PrivateKey<E> {}
+
+/// Constructs a keypair given an RNG and a 64-byte transcript `digest`.
+pub fn keypair<R: Rng, E: Engine>(rng: &mut R, digest: &[u8]) -> (PublicKey<E>, PrivateKey<E>) {
+    assert_eq!(digest.len(), 64);
+
+    let tau = E::Fr::rand(rng);
+    let alpha = E::Fr::rand(rng);
+    let beta = E::Fr::rand(rng);
+
+    let mut op = |x: E::Fr, personalization: u8| -> (E::G1Affine, E::G1Affine, E::G2Affine) {
+        // Sample random g^s
+        let g1_s = E::G1::rand(rng).into_affine();
+        // Compute g^{s*x}
+        let g1_s_x = g1_s.mul(x).into_affine();
+        // Compute BLAKE2b(personalization | transcript | g^s | g^{s*x})
+        let h = compute_g2_s::<E>(digest, &g1_s, &g1_s_x, personalization);
+        // Hash it to a point on G2
+        let g2_s = hash_to_g2::<E>(h.as_ref()).into_affine();
+        // Compute g_2^{s*x}
+        let g2_s_x = g2_s.mul(x).into_affine();
+
+        (g1_s, g1_s_x, g2_s_x)
+    };
+
+    let (tau_g1_s, tau_g1_s_x, tau_g2_s_x) = op(tau, 0);
+    let (alpha_g1_s, alpha_g1_s_x, alpha_g2_s_x) = op(alpha, 1);
+    let (beta_g1_s, beta_g1_s_x, beta_g2_s_x) = op(beta, 2);
+
+    (
+        PublicKey {
+            tau_g1: (tau_g1_s, tau_g1_s_x),
+            alpha_g1: (alpha_g1_s, alpha_g1_s_x),
+            beta_g1: (beta_g1_s, beta_g1_s_x),
+            tau_g2: tau_g2_s_x,
+            alpha_g2: alpha_g2_s_x,
+            beta_g2: beta_g2_s_x,
+        },
+        PrivateKey { tau, alpha, beta },
+    )
+}
+
+impl<E: Engine> PublicKey<E> {
+    /// Serializes the public key to `writer`.
+    pub fn write<P>(&self, writer: &mut [u8], _compression: super::parameters::UseCompression) -> io::Result<()>
+    where
+        P: PowersOfTauParameters,
+    {
+        let mut writer = writer;
+
+        (writer).write_all(self.tau_g1.0.into_uncompressed().as_ref())?;
+        (writer).write_all(self.tau_g1.1.into_uncompressed().as_ref())?;
+        (writer).write_all(self.alpha_g1.0.into_uncompressed().as_ref())?;
+        (writer).write_all(self.alpha_g1.1.into_uncompressed().as_ref())?;
+        (writer).write_all(self.beta_g1.0.into_uncompressed().as_ref())?;
+        (writer).write_all(self.beta_g1.1.into_uncompressed().as_ref())?;
+        (writer).write_all(self.tau_g2.into_uncompressed().as_ref())?;
+        (writer).write_all(self.alpha_g2.into_uncompressed().as_ref())?;
+        (writer).write_all(self.beta_g2.into_uncompressed().as_ref())?;
+
+        Ok(())
+    }
+
+    /// Deserializes the public key from `reader`.
+    pub fn read(mut reader: &[u8]) -> Result<Self, DeserializationError> {
+        fn read_uncompressed<E: Engine, C: CurveAffine<Engine = E, Scalar = E::Fr>>(
+            mut reader: impl Read,
+        ) -> Result<C, DeserializationError> {
+            let mut repr = C::Uncompressed::empty();
+            reader.read_exact(repr.as_mut())?;
+            let v = repr.into_affine()?;
+            Ok(v)
+        }
+
+        let tau_g1_s = read_uncompressed::<E, _>(&mut reader)?;
+        let tau_g1_s_x = read_uncompressed::<E, _>(&mut reader)?;
+        let alpha_g1_s = read_uncompressed::<E, _>(&mut reader)?;
+        let alpha_g1_s_x = read_uncompressed::<E, _>(&mut reader)?;
+        let beta_g1_s = read_uncompressed::<E, _>(&mut reader)?;
+        let beta_g1_s_x = read_uncompressed::<E, _>(&mut reader)?;
+        let tau_g2 = read_uncompressed::<E, _>(&mut reader)?;
+        let alpha_g2 = read_uncompressed::<E, _>(&mut reader)?;
+        let beta_g2 = read_uncompressed::<E, _>(&mut reader)?;
+
+        Ok(PublicKey {
+            tau_g1: (tau_g1_s, tau_g1_s_x),
+            alpha_g1: (alpha_g1_s, alpha_g1_s_x),
+            beta_g1: (beta_g1_s, beta_g1_s_x),
+            tau_g2,
+            alpha_g2,
+            beta_g2,
+        })
+    }
+}